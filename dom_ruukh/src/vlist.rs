@@ -1,19 +1,78 @@
 //! Representation of a list of nodes in VDOM.
 
 use dom::DOMPatch;
+use std::collections::HashMap;
 use std::fmt::{self, Display, Formatter};
+use std::mem;
+use std::rc::Rc;
 use wasm_bindgen::prelude::JsValue;
 use web_api::*;
-use {KeyedVNodes, VNode};
+use {Key, KeyedVNodes, VNode};
 
 /// The representation of a list of vnodes in the vtree.
 #[derive(Debug)]
-pub struct VList(Vec<KeyedVNodes>);
+pub struct VList {
+    /// Children are `Rc`-shared rather than owned outright. `diff` pairs an
+    /// old and a new child that are the very same `Rc` without descending
+    /// into it. Rendering or patching a child goes through `Rc::make_mut`,
+    /// so a child whose `Rc` is also held elsewhere at that point is cloned
+    /// into a fresh `Rc` first, and the list no longer shares it with that
+    /// other holder.
+    children: Vec<Rc<KeyedVNodes>>,
+    /// An empty-text anchor kept in the DOM whenever `children` is empty, so
+    /// `node()` always has a stable node to hand back and the surrounding
+    /// `next`-threading doesn't lose its insertion point when this list
+    /// empties out between other siblings. `None` when `children` is
+    /// non-empty or `use_placeholder` is `false`.
+    placeholder: Option<Node>,
+    use_placeholder: bool,
+}
 
 impl VList {
     /// Constructor to create a list of VNodes.
     pub fn new(list: Vec<KeyedVNodes>) -> VList {
-        VList(list)
+        VList::from_shared(list.into_iter().map(Rc::new).collect())
+    }
+
+    /// Construct a list directly from already-shared children. A child is
+    /// diffed without descending only when it is the same `Rc` as its old
+    /// counterpart. A child still shared with another holder when it is
+    /// rendered is cloned into a fresh `Rc` (see `children`), so a later
+    /// list built from that holder's `Rc` is diffed against it in full.
+    pub fn from_shared(children: Vec<Rc<KeyedVNodes>>) -> VList {
+        VList {
+            children,
+            placeholder: None,
+            use_placeholder: true,
+        }
+    }
+
+    /// Opt out of the empty-list placeholder anchor. Only safe when this
+    /// list is never rendered in a sibling position (e.g. the sole root
+    /// fragment of a component), since without a placeholder `node()`
+    /// returns `None` while the list is empty.
+    pub fn suppress_placeholder(mut self) -> VList {
+        self.use_placeholder = false;
+        self
+    }
+
+    /// Keep the empty-list placeholder in sync with whether `children` is
+    /// currently empty: create one right before `next` the moment the list
+    /// empties out, and drop it again as soon as real children exist.
+    fn sync_placeholder(&mut self, parent: &Node, next: Option<&Node>) -> Result<(), JsValue> {
+        if !self.use_placeholder {
+            return Ok(());
+        }
+        if self.children.is_empty() {
+            if self.placeholder.is_none() {
+                let placeholder = html_document.create_text_node("");
+                parent.insert_before(&placeholder, next)?;
+                self.placeholder = Some(placeholder);
+            }
+        } else if let Some(placeholder) = self.placeholder.take() {
+            parent.remove_child(&placeholder)?;
+        }
+        Ok(())
     }
 }
 
@@ -25,7 +84,7 @@ impl From<VList> for VNode {
 
 impl Display for VList {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        for vnode in self.0.iter() {
+        for vnode in self.children.iter() {
             write!(f, "{}", vnode)?;
         }
         Ok(())
@@ -36,12 +95,91 @@ impl DOMPatch for VList {
     type Node = Node;
 
     fn render_walk(&mut self, parent: Self::Node, next: Option<Self::Node>) -> Result<(), JsValue> {
-        let mut next = next;
-        for vnode in self.0.iter_mut().rev() {
-            vnode.render_walk(parent.clone(), next)?;
-            next = vnode.node();
+        // Walked with an explicit stack of `RenderFrame`s rather than one
+        // Rust call frame per nested list, for the same reason `patch` is
+        // driven through `VListDiff`: a list nested inside a list would
+        // otherwise recurse straight through this function again for every
+        // level of nesting.
+        let children = mem::replace(&mut self.children, Vec::new());
+        let cursor = children.len();
+        let mut frames = vec![RenderFrame {
+            children,
+            cursor,
+            parent: parent.clone(),
+            next,
+            use_placeholder: self.use_placeholder,
+            placeholder: self.placeholder.take(),
+            resume_index: None,
+        }];
+
+        loop {
+            let done = frames.last().map_or(true, |frame| frame.cursor == 0);
+            if done {
+                let finished = frames.pop().unwrap();
+                let mut list = VList {
+                    children: finished.children,
+                    placeholder: finished.placeholder,
+                    use_placeholder: finished.use_placeholder,
+                };
+                list.sync_placeholder(&finished.parent, finished.next.as_ref())?;
+
+                match frames.last_mut() {
+                    None => {
+                        *self = list;
+                        return Ok(());
+                    }
+                    Some(parent_frame) => {
+                        let index = finished.resume_index.expect("non-root frame has a resume index");
+                        if let Some(slot) =
+                            Rc::make_mut(&mut parent_frame.children[index]).list_mut()
+                        {
+                            *slot = list;
+                        }
+                        parent_frame.next = parent_frame.children[index].node();
+                    }
+                }
+                continue;
+            }
+
+            let index = {
+                let frame = frames.last_mut().unwrap();
+                frame.cursor -= 1;
+                frame.cursor
+            };
+            let (child_parent, child_next) = {
+                let frame = frames.last().unwrap();
+                (frame.parent.clone(), frame.next.clone())
+            };
+
+            let is_list = {
+                let frame = frames.last_mut().unwrap();
+                Rc::make_mut(&mut frame.children[index]).list_mut().is_some()
+            };
+
+            if is_list {
+                let nested = {
+                    let frame = frames.last_mut().unwrap();
+                    mem::replace(
+                        Rc::make_mut(&mut frame.children[index]).list_mut().unwrap(),
+                        VList::from_shared(Vec::new()),
+                    )
+                };
+                let nested_cursor = nested.children.len();
+                frames.push(RenderFrame {
+                    children: nested.children,
+                    cursor: nested_cursor,
+                    parent: child_parent,
+                    next: child_next,
+                    use_placeholder: nested.use_placeholder,
+                    placeholder: nested.placeholder,
+                    resume_index: Some(index),
+                });
+            } else {
+                let frame = frames.last_mut().unwrap();
+                Rc::make_mut(&mut frame.children[index]).render_walk(child_parent, child_next)?;
+                frame.next = frame.children[index].node();
+            }
         }
-        Ok(())
     }
 
     fn patch(
@@ -50,43 +188,499 @@ impl DOMPatch for VList {
         parent: Self::Node,
         next: Option<Self::Node>,
     ) -> Result<(), JsValue> {
-        let mut next = next;
-        if let Some(mut old) = old {
-            let old_len = old.0.len();
-            for (index, vnode) in self.0.iter_mut().enumerate().rev() {
-                let old = if index < old_len {
-                    Some(old.0.remove(index))
-                } else {
-                    None
-                };
-                vnode.patch(old, parent.clone(), next)?;
-                next = vnode.node();
-            }
-            old.remove(parent)?;
+        if let Some(old) = old {
+            let new_self = mem::replace(self, VList::from_shared(Vec::new()));
+            *self = new_self.patch_keyed(old, parent, next)?;
+            Ok(())
         } else {
-            for vnode in self.0.iter_mut().rev() {
-                vnode.patch(None, parent.clone(), next)?;
+            let mut next = next;
+            for vnode in self.children.iter_mut().rev() {
+                Rc::make_mut(vnode).patch(None, parent.clone(), next)?;
                 next = vnode.node();
             }
+            self.sync_placeholder(&parent, next.as_ref())?;
+            Ok(())
         }
-        Ok(())
     }
 
     fn remove(self, parent: Self::Node) -> Result<(), JsValue> {
-        for vnode in self.0 {
-            vnode.remove(parent.clone())?;
+        for vnode in self.children {
+            // This child is leaving the tree regardless of how many other
+            // owners its `Rc` still has (e.g. a memoized subtree cached
+            // elsewhere): its DOM node must come out of `parent` either way,
+            // so unwrap when we're the sole owner and fall back to a clone
+            // when we're not, rather than skipping the teardown.
+            unwrap_or_clone(vnode).remove(parent.clone())?;
+        }
+        if let Some(placeholder) = self.placeholder {
+            parent.remove_child(&placeholder)?;
         }
         Ok(())
     }
 
     fn node(&self) -> Option<Node> {
-        self.0.get(0).and_then(|first| first.node())
+        self.children
+            .get(0)
+            .and_then(|first| first.node())
+            .or_else(|| self.placeholder.clone())
     }
 }
 
+/// A single list-level reconciliation step for a `VList`: which old node (if
+/// any) a new node is paired with, and whether it needs to move. Keeping this
+/// plan separate from applying it lets the keyed-diff logic run headless (the
+/// patch vector can be asserted on directly in unit tests) without needing a
+/// live `Node` for the list itself. Applying a patch still defers to each
+/// paired child's own `DOMPatch::patch`, which does touch `web_api`, so this
+/// alone doesn't make the list DOM-free end to end -- that would need the
+/// same headless/apply split carried down into every vnode kind.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Patch {
+    /// Freshly render the new node at `index`.
+    Create { index: usize },
+    /// Remove the old node that used to be at `old_index`; it has no
+    /// counterpart in the new list.
+    Remove { old_index: usize },
+    /// The new node at `index` is paired with the old node at `old_index`
+    /// and already sits in the right relative order; patch it in place.
+    /// When `reuse` is set, the new and old `Rc` point at the very same
+    /// child, so the descend is skipped entirely and the already-mounted
+    /// DOM node is kept as-is.
+    PatchInPlace {
+        index: usize,
+        old_index: usize,
+        reuse: bool,
+    },
+    /// The new node at `index` is paired with the old node at `old_index`
+    /// but falls outside the longest increasing subsequence of matches, so
+    /// it must be moved to its new slot (and, unless `reuse` is set,
+    /// patched in place first).
+    PatchAndMove {
+        index: usize,
+        old_index: usize,
+        reuse: bool,
+    },
+}
+
+impl VList {
+    /// Key-aware reconciliation of the old list against `self`.
+    ///
+    /// Every new node is paired with an old node that carries the same key
+    /// (falling back to positional pairing among the remaining unkeyed runs),
+    /// so that reordering a keyed list moves existing nodes/components
+    /// instead of tearing them down and re-rendering them in place. Moves are
+    /// minimized by keeping the longest increasing subsequence of matched old
+    /// indices untouched and only re-inserting the nodes that fall outside it.
+    fn patch_keyed(self, old: VList, parent: Node, next: Option<Node>) -> Result<VList, JsValue> {
+        self.diff_partial(old, parent, next).finish()
+    }
+
+    /// Begin a resumable reconciliation of `self` against `old`.
+    ///
+    /// `self` is consumed rather than borrowed: the in-progress reconciliation
+    /// becomes the sole owner of the new list's children, so a host holds
+    /// onto the returned `VListDiff` (not a half-emptied `VList`) until it's
+    /// ready to call `finish` and get a complete `VList` back. Internally,
+    /// the returned `VListDiff` holds the work as an explicit stack that
+    /// `step` pops from in `budget`-sized batches; a deeply nested tree of
+    /// lists no longer needs one Rust stack frame per level for this list's
+    /// own children, and a host can spread a large diff across several idle
+    /// callbacks by calling `step` repeatedly instead of blocking on `finish`.
+    pub fn diff_partial(self, old: VList, parent: Node, next: Option<Node>) -> VListDiff {
+        let (patches, old_nodes) = self.diff(&old);
+        VListDiff {
+            frames: vec![Frame {
+                new_nodes: self.children,
+                old_nodes,
+                work: stack_order(patches),
+                parent,
+                next,
+                use_placeholder: self.use_placeholder,
+                placeholder: old.placeholder,
+                resume: None,
+            }],
+        }
+    }
+
+    /// Compute the ordered list of patches that reconciles `old` into
+    /// `self`, without touching `web_api`. Returned alongside is the old
+    /// list's nodes (as `Option`s, indexed by the old position, `Rc`-shared
+    /// with `old` itself), for `VListDiff` to pull individual nodes out of
+    /// as it executes each patch.
+    fn diff(&self, old: &VList) -> (Vec<Patch>, Vec<Option<Rc<KeyedVNodes>>>) {
+        let old_nodes: Vec<Option<Rc<KeyedVNodes>>> =
+            old.children.iter().cloned().map(Some).collect();
+
+        // Map each key to the first old index that carries it. A later old
+        // node repeating a key already in the map is never matched (an
+        // unkeyed new node cannot claim it either), so it is torn down; a
+        // later new node repeating a key finds no entry left and is created
+        // fresh.
+        let mut key_to_old_index: HashMap<Key, usize> = HashMap::new();
+        for (index, node) in old_nodes.iter().enumerate() {
+            if let Some(key) = node.as_ref().and_then(|node| node.key()) {
+                key_to_old_index.entry(key.clone()).or_insert(index);
+            }
+        }
+
+        // Pair every new node with an old index: by key when the new node is
+        // keyed, otherwise with the next free unkeyed old node in order. An
+        // unkeyed node is never allowed to claim a keyed old node, and vice
+        // versa.
+        let mut unkeyed_cursor = 0;
+        let pairing: Vec<Option<usize>> = self
+            .children
+            .iter()
+            .map(|vnode| {
+                if let Some(key) = vnode.key() {
+                    key_to_old_index.remove(key)
+                } else {
+                    while unkeyed_cursor < old_nodes.len() {
+                        let is_free_unkeyed = old_nodes[unkeyed_cursor]
+                            .as_ref()
+                            .map_or(false, |node| node.key().is_none());
+                        let index = unkeyed_cursor;
+                        unkeyed_cursor += 1;
+                        if is_free_unkeyed {
+                            return Some(index);
+                        }
+                    }
+                    None
+                }
+            })
+            .collect();
+
+        // Nodes on the longest increasing subsequence of matched old indices
+        // are already in relative order and can stay where they are; only
+        // the nodes outside the LIS need to be moved to their new slot.
+        let matched_old_indices: Vec<usize> = pairing.iter().filter_map(|p| *p).collect();
+        let lis = longest_increasing_subsequence(&matched_old_indices);
+        let stationary: std::collections::HashSet<usize> =
+            lis.into_iter().map(|i| matched_old_indices[i]).collect();
+
+        // Anything left unmatched in the old list has no counterpart in the
+        // new list and must be torn down.
+        let matched: std::collections::HashSet<usize> = pairing.iter().filter_map(|p| *p).collect();
+        let mut patches: Vec<Patch> = (0..old_nodes.len())
+            .filter(|old_index| !matched.contains(old_index))
+            .map(|old_index| Patch::Remove { old_index })
+            .collect();
+
+        patches.extend(pairing.iter().enumerate().map(|(index, pair)| match pair {
+            Some(old_index) => {
+                let reuse = old_nodes[*old_index]
+                    .as_ref()
+                    .map_or(false, |old_node| Rc::ptr_eq(old_node, &self.children[index]));
+                if stationary.contains(old_index) {
+                    Patch::PatchInPlace {
+                        index,
+                        old_index: *old_index,
+                        reuse,
+                    }
+                } else {
+                    Patch::PatchAndMove {
+                        index,
+                        old_index: *old_index,
+                        reuse,
+                    }
+                }
+            }
+            None => Patch::Create { index },
+        }));
+
+        (patches, old_nodes)
+    }
+}
+
+/// Re-order a patch vector (as produced by `diff`, ascending by new index
+/// with removals first) into the order an explicit LIFO work stack should
+/// hold them in, so that popping one at a time reproduces the original
+/// right-to-left `next`-threading: removals first (order doesn't matter,
+/// they touch no `next`), then the remaining patches from the highest index
+/// down to the lowest.
+fn stack_order(patches: Vec<Patch>) -> Vec<Patch> {
+    let (removes, mut rest): (Vec<Patch>, Vec<Patch>) =
+        patches.into_iter().partition(|patch| matches!(patch, Patch::Remove { .. }));
+    rest.extend(removes);
+    rest
+}
+
+/// One level of `render_walk`'s non-recursive walk: the children left to
+/// visit (in reverse order, like the original loop), threading `next` as it
+/// goes. Visiting a nested `VList` child pushes another `RenderFrame` rather
+/// than recursing into that child's own `render_walk`.
+struct RenderFrame {
+    children: Vec<Rc<KeyedVNodes>>,
+    cursor: usize,
+    parent: Node,
+    next: Option<Node>,
+    use_placeholder: bool,
+    placeholder: Option<Node>,
+    /// Index into the frame below's `children` this frame is walking the
+    /// nested list for; `None` for the root frame.
+    resume_index: Option<usize>,
+}
+
+/// Where a finished nested frame's result needs to be written back into the
+/// frame below once it drains: the slot in the parent frame's `new_nodes`
+/// that owns the nested list, and whether the parent's own patch for that
+/// slot still owes it a `move_before`.
+struct Resume {
+    index: usize,
+    needs_move: bool,
+}
+
+/// One level of an in-progress `VList` reconciliation: the new/old node
+/// arrays being paired up and the work remaining for just that level.
+/// Patching a child that is itself a nested `VList` pushes another `Frame` on
+/// top of `VListDiff::frames` instead of recursing through that child's own
+/// `patch`, so a list nested inside a list costs a `Vec` push rather than
+/// another Rust call frame.
+struct Frame {
+    new_nodes: Vec<Rc<KeyedVNodes>>,
+    old_nodes: Vec<Option<Rc<KeyedVNodes>>>,
+    work: Vec<Patch>,
+    parent: Node,
+    next: Option<Node>,
+    use_placeholder: bool,
+    placeholder: Option<Node>,
+    /// `None` for the root frame, `Some` for a frame pushed to reconcile a
+    /// nested list found while running a patch in the frame below.
+    resume: Option<Resume>,
+}
+
+/// A paused or in-progress reconciliation of a `VList`, driven one
+/// `step`-sized batch of instructions at a time instead of recursing through
+/// every paired child in a single synchronous call.
+pub struct VListDiff {
+    frames: Vec<Frame>,
+}
+
+impl VListDiff {
+    /// Process at most `budget` instructions from the work stack. Returns
+    /// `Ok(true)` if instructions remain, so a host can spread the diff
+    /// across several idle callbacks instead of blocking on `finish`.
+    pub fn step(&mut self, budget: usize) -> Result<bool, JsValue> {
+        for _ in 0..budget {
+            if !self.has_work() {
+                return Ok(false);
+            }
+            let popped = self
+                .frames
+                .last_mut()
+                .and_then(|frame| frame.work.pop());
+            match popped {
+                Some(patch) => self.run_patch(patch)?,
+                None => self.resolve_frame()?,
+            }
+        }
+        Ok(self.has_work())
+    }
+
+    /// Whether any instruction remains: a queued patch, or a nested frame
+    /// still waiting to be resolved into the frame below. The root frame is
+    /// only popped by `finish`, so a root with an empty work stack and no
+    /// frames above it counts as nothing left to do.
+    fn has_work(&self) -> bool {
+        self.frames.len() > 1 || self.frames.last().map_or(false, |frame| !frame.work.is_empty())
+    }
+
+    /// Run every remaining instruction to completion and hand back a
+    /// complete, ready-to-store `VList` (placeholder included) rather than
+    /// leaving the caller to reassemble one from raw parts.
+    pub fn finish(mut self) -> Result<VList, JsValue> {
+        while self.step(usize::max_value())? {}
+        let root = self.frames.pop().expect("root frame always present");
+        let mut list = VList {
+            children: root.new_nodes,
+            placeholder: root.placeholder,
+            use_placeholder: root.use_placeholder,
+        };
+        list.sync_placeholder(&root.parent, root.next.as_ref())?;
+        Ok(list)
+    }
+
+    fn run_patch(&mut self, patch: Patch) -> Result<(), JsValue> {
+        match patch {
+            Patch::Remove { old_index } => {
+                let frame = self.frames.last_mut().unwrap();
+                if let Some(node) = frame.old_nodes[old_index].take() {
+                    // As in `DOMPatch::remove`, this node is leaving the
+                    // reconciled list either way, so its DOM node must be
+                    // torn down even when another owner is still holding
+                    // onto the same `Rc`.
+                    unwrap_or_clone(node).remove(frame.parent.clone())?;
+                }
+                Ok(())
+            }
+            Patch::Create { index } => {
+                let frame = self.frames.last_mut().unwrap();
+                let (parent, next) = (frame.parent.clone(), frame.next.clone());
+                Rc::make_mut(&mut frame.new_nodes[index]).patch(None, parent, next)?;
+                frame.next = frame.new_nodes[index].node();
+                Ok(())
+            }
+            Patch::PatchInPlace {
+                index,
+                old_index,
+                reuse,
+            } => self.run_paired(index, old_index, reuse, false),
+            Patch::PatchAndMove {
+                index,
+                old_index,
+                reuse,
+            } => self.run_paired(index, old_index, reuse, true),
+        }
+    }
+
+    /// Run a `PatchInPlace`/`PatchAndMove` pairing. When both the new and
+    /// old nodes are nested `VList`s, their reconciliation is pushed as a
+    /// frame onto this same stack rather than reached by recursing into
+    /// `KeyedVNodes::patch`, which is what would otherwise turn a list of
+    /// lists of lists into one Rust stack frame per level. Any other paired
+    /// vnode kind still patches through its own `DOMPatch::patch`, the same
+    /// as before.
+    fn run_paired(
+        &mut self,
+        index: usize,
+        old_index: usize,
+        reuse: bool,
+        needs_move: bool,
+    ) -> Result<(), JsValue> {
+        let frame = self.frames.last_mut().unwrap();
+        if reuse {
+            if needs_move {
+                frame.new_nodes[index].move_before(&frame.parent, frame.next.as_ref())?;
+            }
+            frame.next = frame.new_nodes[index].node();
+            return Ok(());
+        }
+
+        let mut old_vnode = frame.old_nodes[old_index].take().map(unwrap_or_clone);
+        let parent = frame.parent.clone();
+        let next = frame.next.clone();
+
+        let both_lists = old_vnode.as_mut().map_or(false, |old| old.list_mut().is_some())
+            && Rc::make_mut(&mut frame.new_nodes[index]).list_mut().is_some();
+
+        if both_lists {
+            let new_list = mem::replace(
+                Rc::make_mut(&mut frame.new_nodes[index]).list_mut().unwrap(),
+                VList::from_shared(Vec::new()),
+            );
+            let old_list = mem::replace(
+                old_vnode.as_mut().unwrap().list_mut().unwrap(),
+                VList::from_shared(Vec::new()),
+            );
+            self.push_frame(new_list, old_list, parent, next, Resume { index, needs_move });
+            return Ok(());
+        }
+
+        Rc::make_mut(&mut frame.new_nodes[index]).patch(old_vnode, parent, next)?;
+        if needs_move {
+            frame.new_nodes[index].move_before(&frame.parent, frame.next.as_ref())?;
+        }
+        frame.next = frame.new_nodes[index].node();
+        Ok(())
+    }
+
+    fn push_frame(
+        &mut self,
+        new_list: VList,
+        old_list: VList,
+        parent: Node,
+        next: Option<Node>,
+        resume: Resume,
+    ) {
+        let (patches, old_nodes) = new_list.diff(&old_list);
+        self.frames.push(Frame {
+            new_nodes: new_list.children,
+            old_nodes,
+            work: stack_order(patches),
+            parent,
+            next,
+            use_placeholder: new_list.use_placeholder,
+            placeholder: old_list.placeholder,
+            resume: Some(resume),
+        });
+    }
+
+    /// Pop a drained (non-root) frame, reassemble its reconciled nodes back
+    /// into the nested-list slot its `Resume` points at in the frame below,
+    /// and give that frame the `next`/move bookkeeping its patch still owed.
+    fn resolve_frame(&mut self) -> Result<(), JsValue> {
+        let finished = self.frames.pop().expect("resolve_frame needs a non-root frame");
+        let resume = finished.resume.expect("non-root frames always carry a Resume");
+
+        let mut child_list = VList {
+            children: finished.new_nodes,
+            placeholder: finished.placeholder,
+            use_placeholder: finished.use_placeholder,
+        };
+        child_list.sync_placeholder(&finished.parent, finished.next.as_ref())?;
+
+        let parent = self.frames.last_mut().unwrap();
+        if let Some(slot) = Rc::make_mut(&mut parent.new_nodes[resume.index]).list_mut() {
+            *slot = child_list;
+        }
+        if resume.needs_move {
+            parent.new_nodes[resume.index].move_before(&parent.parent, parent.next.as_ref())?;
+        }
+        parent.next = parent.new_nodes[resume.index].node();
+        Ok(())
+    }
+}
+
+/// Pull a `KeyedVNodes` out of its `Rc` without cloning when this is the
+/// sole owner (the overwhelmingly common case, since a fresh `Rc::new` is
+/// wrapped around each child every render unless explicitly hoisted and
+/// reused), falling back to a real clone on the rare path where it's still
+/// shared.
+fn unwrap_or_clone(rc: Rc<KeyedVNodes>) -> KeyedVNodes {
+    Rc::try_unwrap(rc).unwrap_or_else(|rc| (*rc).clone())
+}
+
+/// Indices (into `values`) of one longest strictly increasing subsequence.
+fn longest_increasing_subsequence(values: &[usize]) -> Vec<usize> {
+    if values.is_empty() {
+        return Vec::new();
+    }
+
+    // `tails[k]` holds the index (into `values`) of the smallest tail value
+    // of an increasing subsequence of length `k + 1`.
+    let mut tails: Vec<usize> = Vec::new();
+    let mut predecessors: Vec<Option<usize>> = vec![None; values.len()];
+
+    for (index, &value) in values.iter().enumerate() {
+        let pos = tails
+            .binary_search_by(|&tail_index| values[tail_index].cmp(&value))
+            .unwrap_or_else(|pos| pos);
+
+        if pos > 0 {
+            predecessors[index] = Some(tails[pos - 1]);
+        }
+
+        if pos == tails.len() {
+            tails.push(index);
+        } else {
+            tails[pos] = index;
+        }
+    }
+
+    let mut lis = Vec::with_capacity(tails.len());
+    let mut cursor = tails.last().copied();
+    while let Some(index) = cursor {
+        lis.push(index);
+        cursor = predecessors[index];
+    }
+    lis.reverse();
+    lis
+}
+
 #[cfg(test)]
 mod test {
-    use super::VList;
+    use super::{longest_increasing_subsequence, Patch, VList};
+    use std::rc::Rc;
     use velement::VElement;
     use vtext::VText;
     use KeyedVNodes;
@@ -99,6 +693,99 @@ mod test {
         ]);
         assert_eq!(format!("{}", list), "First of the node<input>");
     }
+
+    #[test]
+    fn should_compute_longest_increasing_subsequence() {
+        let values = vec![2, 0, 1, 3];
+        let lis = longest_increasing_subsequence(&values);
+        let subsequence: Vec<usize> = lis.iter().map(|&i| values[i]).collect();
+        assert_eq!(subsequence, vec![0, 1, 3]);
+    }
+
+    #[test]
+    fn should_diff_reordered_keyed_list_without_touching_the_dom() {
+        let old = VList::new(vec![
+            KeyedVNodes::keyed(1, VText::text("one")),
+            KeyedVNodes::keyed(2, VText::text("two")),
+            KeyedVNodes::keyed(3, VText::text("three")),
+        ]);
+        let new = VList::new(vec![
+            KeyedVNodes::keyed(3, VText::text("three")),
+            KeyedVNodes::keyed(1, VText::text("one")),
+            KeyedVNodes::keyed(2, VText::text("two")),
+        ]);
+
+        let (patches, _old_nodes) = new.diff(&old);
+
+        // 1 and 2 keep their relative order (the LIS) and are just patched
+        // in place; only 3 needs to move to the front. None of them share an
+        // `Rc` with `old`, so `reuse` is false throughout.
+        assert_eq!(
+            patches,
+            vec![
+                Patch::PatchAndMove {
+                    index: 0,
+                    old_index: 2,
+                    reuse: false,
+                },
+                Patch::PatchInPlace {
+                    index: 1,
+                    old_index: 0,
+                    reuse: false,
+                },
+                Patch::PatchInPlace {
+                    index: 2,
+                    old_index: 1,
+                    reuse: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn should_diff_insertions_and_removals_by_key() {
+        let old = VList::new(vec![
+            KeyedVNodes::keyed(1, VText::text("one")),
+            KeyedVNodes::keyed(2, VText::text("two")),
+        ]);
+        let new = VList::new(vec![
+            KeyedVNodes::keyed(1, VText::text("one")),
+            KeyedVNodes::keyed(3, VText::text("three")),
+        ]);
+
+        let (patches, _old_nodes) = new.diff(&old);
+
+        assert_eq!(
+            patches,
+            vec![
+                Patch::Remove { old_index: 1 },
+                Patch::PatchInPlace {
+                    index: 0,
+                    old_index: 0,
+                    reuse: false,
+                },
+                Patch::Create { index: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn should_skip_diffing_a_child_shared_by_rc() {
+        let memoized = Rc::new(KeyedVNodes::keyed(1, VText::text("constant")));
+        let old = VList::from_shared(vec![Rc::clone(&memoized)]);
+        let new = VList::from_shared(vec![Rc::clone(&memoized)]);
+
+        let (patches, _old_nodes) = new.diff(&old);
+
+        assert_eq!(
+            patches,
+            vec![Patch::PatchInPlace {
+                index: 0,
+                old_index: 0,
+                reuse: true,
+            }]
+        );
+    }
 }
 
 #[cfg(test)]
@@ -148,4 +835,71 @@ pub mod wasm_test {
 
         assert_eq!(div.inner_html(), "<div></div>Hello World!How are you?");
     }
+
+    #[wasm_bindgen_test]
+    fn should_reorder_keyed_list_without_rerendering_untouched_nodes() {
+        let mut list = VList::new(vec![
+            KeyedVNodes::keyed(1, VText::text("one")),
+            KeyedVNodes::keyed(2, VText::text("two")),
+            KeyedVNodes::keyed(3, VText::text("three")),
+        ]);
+        let div = container();
+        list.patch(None, div.clone().into(), None)
+            .expect("To patch div");
+
+        let mut reordered = VList::new(vec![
+            KeyedVNodes::keyed(3, VText::text("three")),
+            KeyedVNodes::keyed(1, VText::text("one")),
+            KeyedVNodes::keyed(2, VText::text("two")),
+        ]);
+        reordered
+            .patch(Some(list), div.clone().into(), None)
+            .expect("To patch div");
+
+        assert_eq!(div.inner_html(), "threeonetwo");
+    }
+
+    #[wasm_bindgen_test]
+    fn should_step_through_a_diff_with_a_budget() {
+        let mut list = VList::new(vec![
+            KeyedVNodes::keyed(1, VText::text("one")),
+            KeyedVNodes::keyed(2, VText::text("two")),
+            KeyedVNodes::keyed(3, VText::text("three")),
+        ]);
+        let div = container();
+        list.patch(None, div.clone().into(), None)
+            .expect("To patch div");
+
+        let reordered = VList::new(vec![
+            KeyedVNodes::keyed(3, VText::text("three")),
+            KeyedVNodes::keyed(1, VText::text("one")),
+            KeyedVNodes::keyed(2, VText::text("two")),
+        ]);
+        let mut diff = reordered.diff_partial(list, div.clone().into(), None);
+
+        // Three instructions on the work stack; budgeting one at a time
+        // should leave work remaining until the final step.
+        assert_eq!(diff.step(1).expect("To step"), true);
+        assert_eq!(diff.step(1).expect("To step"), true);
+        assert_eq!(diff.step(1).expect("To step"), false);
+
+        assert_eq!(div.inner_html(), "threeonetwo");
+    }
+
+    #[wasm_bindgen_test]
+    fn should_keep_a_placeholder_anchor_while_the_list_is_empty() {
+        let mut list = VList::new(vec![]);
+        let div = container();
+        list.patch(None, div.clone().into(), None)
+            .expect("To patch div");
+
+        assert!(list.node().is_some(), "an empty list should anchor a placeholder");
+
+        let mut filled = VList::new(vec![KeyedVNodes::unkeyed(VText::text("Hi!"))]);
+        filled
+            .patch(Some(list), div.clone().into(), None)
+            .expect("To patch div");
+
+        assert_eq!(div.inner_html(), "Hi!");
+    }
 }